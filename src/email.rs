@@ -0,0 +1,59 @@
+use std::collections::{HashMap, HashSet};
+
+pub const INDEXED_FIELDS: [&str; 5] = ["from", "to", "cc", "subject", "date"];
+
+pub struct ParsedEmail {
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+// Splits an RFC-822 message into its header block and body, unfolding continuation
+// lines (those starting with whitespace) into the header they belong to.
+pub fn parse_email(raw: &str) -> ParsedEmail {
+    let mut lines = raw.lines();
+    let mut headers: HashMap<String, String> = HashMap::new();
+    let mut current_field: Option<String> = None;
+
+    for line in &mut lines {
+        if line.is_empty() {
+            break;
+        }
+
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(field) = &current_field {
+                if let Some(value) = headers.get_mut(field) {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+            }
+            continue;
+        }
+
+        match line.split_once(':') {
+            Some((name, value)) => {
+                let field = name.trim().to_lowercase();
+                headers.insert(field.clone(), value.trim().to_string());
+                current_field = Some(field);
+            }
+            None => current_field = None,
+        }
+    }
+
+    let body = lines.collect::<Vec<&str>>().join("\n");
+
+    ParsedEmail { headers, body }
+}
+
+// Flattens per-document field terms into field -> word -> documents, so a query like
+// `from:skilling` can look up matching documents directly.
+pub fn build_field_index(document_field_terms: HashMap<String, HashMap<String, HashSet<String>>>) -> HashMap<String, HashMap<String, HashSet<String>>> {
+    document_field_terms.into_iter().fold(HashMap::new(), |mut acc, (doc, fields)| {
+        fields.into_iter().for_each(|(field, terms)| {
+            let field_map = acc.entry(field).or_insert_with(HashMap::new);
+            terms.into_iter().for_each(|term| {
+                field_map.entry(term).or_insert_with(HashSet::new).insert(doc.clone());
+            });
+        });
+        acc
+    })
+}
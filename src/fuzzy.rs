@@ -0,0 +1,174 @@
+use std::collections::{BTreeMap, HashMap};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use qp_trie::{wrapper::BString, Trie};
+
+// A plain char-keyed trie over the vocabulary. Built once per fuzzy query from the
+// scored trie's keys so the walk below can share one DP column per depth across every
+// key with a common prefix, instead of re-running edit distance from scratch per word.
+#[derive(Default)]
+struct FuzzyNode {
+    children: BTreeMap<char, FuzzyNode>,
+    word: Option<String>,
+}
+
+impl FuzzyNode {
+    fn insert(&mut self, word: &str) {
+        let mut node = self;
+        for ch in word.chars() {
+            node = node.children.entry(ch).or_default();
+        }
+        node.word = Some(word.to_string());
+    }
+}
+
+// Computes the next Damerau-Levenshtein DP column for `path`'s last character, given
+// the previous column (depth - 1) and the one before that (depth - 2, needed to look
+// back far enough to detect a transposition).
+fn next_column(query: &[char], prev_col: &[usize], prev_prev_col: Option<&[usize]>, path: &[char]) -> Vec<usize> {
+    let depth = path.len();
+    let new_char = path[depth - 1];
+    let query_len = query.len();
+
+    let mut col = vec![0; query_len + 1];
+    col[0] = depth;
+
+    for i in 1..=query_len {
+        let cost = if query[i - 1] == new_char { 0 } else { 1 };
+        let mut value = (prev_col[i] + 1)
+            .min(col[i - 1] + 1)
+            .min(prev_col[i - 1] + cost);
+
+        if depth > 1 && i > 1 && query[i - 1] == path[depth - 2] && query[i - 2] == new_char {
+            if let Some(prev_prev_col) = prev_prev_col {
+                value = value.min(prev_prev_col[i - 2] + 1);
+            }
+        }
+
+        col[i] = value;
+    }
+
+    col
+}
+
+// Recursively descends the char trie, carrying the DP column for the current depth
+// and pruning any branch whose column minimum already exceeds `max_distance` rather
+// than visiting every descendant key.
+fn walk(node: &FuzzyNode, query: &[char], prev_col: &[usize], prev_prev_col: Option<&[usize]>, path: &mut Vec<char>, max_distance: usize, matches: &mut Vec<(String, usize)>) {
+    if let Some(word) = &node.word {
+        let distance = prev_col[query.len()];
+        if distance <= max_distance {
+            matches.push((word.clone(), distance));
+        }
+    }
+
+    for (&ch, child) in &node.children {
+        path.push(ch);
+        let col = next_column(query, prev_col, prev_prev_col, path);
+
+        if col.iter().min().copied().unwrap_or(0) <= max_distance {
+            walk(child, query, &col, Some(prev_col), path, max_distance, matches);
+        }
+
+        path.pop();
+    }
+}
+
+// Builds a char trie over the vocabulary and walks it once, sharing DP state across
+// keys with a common prefix and pruning whole subtrees whose distance can't come back
+// within `max_distance`, rather than scoring every vocabulary word independently.
+// Keeps the best (distance, summed score) seen per document across matched terms.
+pub fn fuzzy_resolve(trie: &mut Trie<BString, BTreeMap<BigDecimal, String>>, query: &str, max_distance: usize) -> HashMap<String, (usize, f64)> {
+    let query_chars: Vec<char> = query.chars().collect();
+
+    let mut root = FuzzyNode::default();
+    for (word, _) in trie.iter() {
+        root.insert(word.as_str());
+    }
+
+    let initial_col: Vec<usize> = (0..=query_chars.len()).collect();
+    let mut matches = Vec::new();
+    walk(&root, &query_chars, &initial_col, None, &mut Vec::new(), max_distance, &mut matches);
+
+    matches.into_iter().fold(HashMap::new(), |mut acc, (word, distance)| {
+        if let Some(doc_scores) = trie.get_mut(&BString::from(word)) {
+            doc_scores.iter().for_each(|(score, doc)| {
+                let score = score.to_f64().unwrap_or(0.0);
+                acc.entry(doc.clone())
+                    .and_modify(|(best_distance, best_score)| {
+                        *best_distance = (*best_distance).min(distance);
+                        *best_score += score;
+                    })
+                    .or_insert((distance, score));
+            });
+        }
+
+        acc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::FromPrimitive;
+
+    use super::*;
+
+    // Runs a single candidate word through the real `walk`/`next_column` machinery
+    // (a one-word char trie) and returns the distance `walk` found for it, if any.
+    fn distance(query: &str, candidate: &str, max_distance: usize) -> Option<usize> {
+        let mut root = FuzzyNode::default();
+        root.insert(candidate);
+
+        let query_chars: Vec<char> = query.chars().collect();
+        let initial_col: Vec<usize> = (0..=query_chars.len()).collect();
+        let mut matches = Vec::new();
+        walk(&root, &query_chars, &initial_col, None, &mut Vec::new(), max_distance, &mut matches);
+
+        matches.into_iter().find(|(word, _)| word == candidate).map(|(_, distance)| distance)
+    }
+
+    #[test]
+    fn identical_strings_are_zero_distance() {
+        assert_eq!(distance("enron", "enron", 2), Some(0));
+    }
+
+    #[test]
+    fn a_single_substitution_is_distance_one() {
+        assert_eq!(distance("enron", "enrot", 2), Some(1));
+    }
+
+    #[test]
+    fn an_adjacent_transposition_is_distance_one() {
+        // Plain Levenshtein would need a delete + insert (distance 2) for this.
+        assert_eq!(distance("enron", "enrno", 2), Some(1));
+    }
+
+    #[test]
+    fn distance_beyond_the_bound_is_none() {
+        assert_eq!(distance("enron", "legal", 2), None);
+    }
+
+    #[test]
+    fn length_difference_alone_can_exceed_the_bound() {
+        assert_eq!(distance("a", "abcd", 1), None);
+    }
+
+    #[test]
+    fn fuzzy_resolve_records_the_distance_of_exact_and_nearby_matches() {
+        let mut trie = Trie::new();
+        trie.insert(BString::from("enron"), BTreeMap::from([
+            (BigDecimal::from_f64(1.0).unwrap(), "doc1".to_string()),
+        ]));
+        trie.insert(BString::from("enrot"), BTreeMap::from([
+            (BigDecimal::from_f64(1.0).unwrap(), "doc2".to_string()),
+        ]));
+        trie.insert(BString::from("legal"), BTreeMap::from([
+            (BigDecimal::from_f64(1.0).unwrap(), "doc3".to_string()),
+        ]));
+
+        let matches = fuzzy_resolve(&mut trie, "enron", 1);
+
+        assert_eq!(matches["doc1"].0, 0);
+        assert_eq!(matches["doc2"].0, 1);
+        assert!(!matches.contains_key("doc3"));
+    }
+}
@@ -1,15 +1,37 @@
+mod scoring;
+mod query;
+mod fuzzy;
+mod persistence;
+mod tokenizer;
+mod stats;
+mod email;
+
 use std::cmp::Ordering;
 use std::io::{self, Read};
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
+use std::time::SystemTime;
 use lazy_static::lazy_static;
 use bigdecimal::{FromPrimitive, BigDecimal};
 use qp_trie::{wrapper::BString, Trie};
 
+use scoring::{ScoringMode, calc_corpus_stats, calc_bm25, calc_tf_idf, calc_inverse_document_freq};
+use query::{Op, SearchIndex, parse_query, eval_query};
+use fuzzy::fuzzy_resolve;
+use persistence::PersistedIndex;
+use tokenizer::{TokenizerConfig, load_stop_words};
+use email::{INDEXED_FIELDS, parse_email, build_field_index};
+
+const DEFAULT_SOURCE_DIR: &str = "/home/qwuke/enron_search_engine/resources/enron/";
+const DEFAULT_DATA_DIR: &str = "data";
+const DEFAULT_MIN_TOKEN_LENGTH: usize = 2;
+const DEFAULT_MAX_TOKEN_LENGTH: usize = 32;
+const DEFAULT_STATS_LIMIT: usize = 10;
+
 lazy_static! {
-    static ref PUNCTUATION_CHARS: HashSet<String> = vec!["!", "\"", "#", "$", "%", 
+    pub(crate) static ref PUNCTUATION_CHARS: HashSet<String> = vec!["!", "\"", "#", "$", "%",
         "&", "'", "(", ")", "*", "+", ",", ";", ".", "/", ":", ",", "<", "=",
         ">", "?", "@", "[", "\\", "]", "^", "_", "`", "{", "|", "}", "~", "-"]
         .iter()
@@ -18,64 +40,255 @@ lazy_static! {
 }
 
 fn main() {
-    let mut args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    if args.is_empty() {
+        println!("Please use a subcommand: build-index | search | stats");
+        return;
+    }
+    let subcommand = args.remove(0);
+
+    match subcommand.as_str() {
+        "build-index" => run_build_index(args),
+        "search" => run_search(args),
+        "stats" => run_stats(args),
+        other => println!("Unknown subcommand '{}'. Expected 'build-index', 'search' or 'stats'.", other),
+    }
+}
+
+fn run_build_index(args: Vec<String>) {
+    let (scoring_mode, args) = parse_scoring_flag(args);
+    let (tokenizer_config, args) = parse_tokenizer_flags(args);
+    let (source_dir, args) = parse_value_flag(args, "--source", DEFAULT_SOURCE_DIR.to_string());
+    let (data_dir, _args) = parse_value_flag(args, "--data-dir", DEFAULT_DATA_DIR.to_string());
+
+    let index = build_index(&source_dir, scoring_mode, &tokenizer_config);
+    persistence::save(&data_dir, &index).expect("Could not write index to data directory");
+
+    println!("Index built from {} and saved to {}", source_dir, data_dir);
+}
+
+fn run_search(args: Vec<String>) {
+    let (scoring_mode, args) = parse_scoring_flag(args);
+    let (default_op, args) = parse_default_op_flag(args);
+    let (fuzzy_distance, args) = parse_fuzzy_flag(args);
+    let (tokenizer_config, args) = parse_tokenizer_flags(args);
+    let (source_dir, args) = parse_value_flag(args, "--source", DEFAULT_SOURCE_DIR.to_string());
+    let (data_dir, positional_args) = parse_value_flag(args, "--data-dir", DEFAULT_DATA_DIR.to_string());
 
-    let search_term = args.pop().expect("to have single argument");
-    if args.len() != 1 {
+    if positional_args.len() != 1 {
         println!("Please use a single argument");
         return;
     }
-    
+    let search_term = &positional_args[0];
+
     println!("Searching for {}", search_term);
 
-    let email_file_paths = get_email_paths_from_dir("/home/qwuke/enron_search_engine/resources/enron/").expect("Cannot read files");
+    let index = load_or_build_index(&source_dir, &data_dir, scoring_mode, &tokenizer_config);
+    let field_index = index.field_index;
+    let mut search_trie = build_search_trie(index.scored_documents);
 
-    let document_word_freq = get_document_word_freq(email_file_paths); 
-    
-    let inverse_document_frequency = calc_inverse_document_freq(document_word_freq.clone());
+    if let Some(max_distance) = fuzzy_distance {
+        fuzzy_search(search_term, max_distance, &tokenizer_config, &mut search_trie);
+    } else {
+        let mut search_index = SearchIndex { body_trie: &mut search_trie, field_index: &field_index };
+        search(search_term, default_op, &tokenizer_config, &mut search_index);
+    }
+}
 
-    let tf_idf = calc_tf_idf(document_word_freq, inverse_document_frequency);
+// Prints a CSV of the `--limit` most frequent vocabulary terms, with their document
+// frequency and total occurrence count, so stop-word and length cutoffs can be tuned.
+fn run_stats(args: Vec<String>) {
+    let (scoring_mode, args) = parse_scoring_flag(args);
+    let (limit, args) = parse_value_flag(args, "--limit", DEFAULT_STATS_LIMIT.to_string());
+    let limit = limit.parse::<usize>().expect("--limit must be a number");
+    let (tokenizer_config, args) = parse_tokenizer_flags(args);
+    let (source_dir, args) = parse_value_flag(args, "--source", DEFAULT_SOURCE_DIR.to_string());
+    let (data_dir, _args) = parse_value_flag(args, "--data-dir", DEFAULT_DATA_DIR.to_string());
 
-    let mut search_trie = build_search_trie(tf_idf);
-    
-    search(&search_term, &mut search_trie);
+    let index = load_or_build_index(&source_dir, &data_dir, scoring_mode, &tokenizer_config);
+    let terms = stats::top_terms(&index.corpus_stats, limit);
+    stats::print_csv(&terms);
 }
 
-fn search(input: &str, search_trie: &mut Trie<BString, BTreeMap<BigDecimal, String>>) {
-    let sanitized_input = input
-        .to_lowercase()
-        .chars()
-        .filter(|&ch| !PUNCTUATION_CHARS.contains(&ch.to_string()))
-        .collect::<String>();
+// Loads the on-disk index if it's at least as fresh as the source corpus, otherwise
+// re-indexes from scratch and persists the result so the next `search` can reuse it.
+fn load_or_build_index(source_dir: &str, data_dir: &str, scoring_mode: ScoringMode, tokenizer_config: &TokenizerConfig) -> PersistedIndex {
+    let (email_file_paths, source_mtime) = get_email_paths_from_dir(source_dir).expect("Cannot read files");
+    let source_mtime = epoch_secs(source_mtime);
 
-    let mut matched_prefixes: Vec<(&BString, String, BigDecimal)> = search_trie.iter_prefix_mut(&BString::from(sanitized_input.clone()))
-        .flat_map(|(word, map)| {
-            let mut temp_vec = Vec::new();
-            for _i in 1..10 {
-                if let Some((score, doc)) =  map.pop_last() {
-                    temp_vec.push((word, doc.to_owned(), score.to_owned()));
-                };
-            }
-            temp_vec
-        })
-        .collect();
+    if let Some(index) = persistence::load_if_fresh(data_dir, source_mtime, scoring_mode, tokenizer_config) {
+        return index;
+    }
+
+    let document_word_freq = get_document_word_freq(&email_file_paths, tokenizer_config);
+    let document_field_terms = get_document_field_terms(&email_file_paths, tokenizer_config);
+    let index = index_from_word_freq(document_word_freq, document_field_terms, scoring_mode, tokenizer_config, source_mtime);
+    persistence::save(data_dir, &index).expect("Could not write index to data directory");
+    index
+}
+
+fn build_index(source_dir: &str, scoring_mode: ScoringMode, tokenizer_config: &TokenizerConfig) -> PersistedIndex {
+    let (email_file_paths, source_mtime) = get_email_paths_from_dir(source_dir).expect("Cannot read files");
+    let document_word_freq = get_document_word_freq(&email_file_paths, tokenizer_config);
+    let document_field_terms = get_document_field_terms(&email_file_paths, tokenizer_config);
+    index_from_word_freq(document_word_freq, document_field_terms, scoring_mode, tokenizer_config, epoch_secs(source_mtime))
+}
+
+fn index_from_word_freq(
+    document_word_freq: HashMap<String, HashMap<String, u64>>,
+    document_field_terms: HashMap<String, HashMap<String, HashSet<String>>>,
+    scoring_mode: ScoringMode,
+    tokenizer_config: &TokenizerConfig,
+    source_mtime: u64,
+) -> PersistedIndex {
+    let corpus_stats = calc_corpus_stats(&document_word_freq);
+    let field_index = build_field_index(document_field_terms);
+
+    let scored_documents = match scoring_mode {
+        ScoringMode::TfIdf => {
+            let inverse_document_frequency = calc_inverse_document_freq(document_word_freq.clone());
+            calc_tf_idf(document_word_freq, inverse_document_frequency)
+        }
+        ScoringMode::Bm25 => calc_bm25(document_word_freq, &corpus_stats),
+    };
+
+    let tokenizer_fingerprint = tokenizer_config.fingerprint();
+
+    PersistedIndex { scored_documents, corpus_stats, field_index, source_mtime, scoring_mode, tokenizer_fingerprint }
+}
+
+fn epoch_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+// Pulls out `--scoring <tfidf|bm25>` from the argument list, defaulting to tf-idf,
+// and returns whatever's left for positional parsing.
+fn parse_scoring_flag(args: Vec<String>) -> (ScoringMode, Vec<String>) {
+    let mut scoring_mode = ScoringMode::TfIdf;
+    let mut remaining = Vec::new();
+
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--scoring" {
+            let value = iter.next().expect("--scoring requires a value");
+            scoring_mode = ScoringMode::from_flag(&value).expect("Unknown scoring mode");
+        } else {
+            remaining.push(arg);
+        }
+    }
+
+    (scoring_mode, remaining)
+}
+
+// Pulls out `--and`, which makes unmarked terms between operators combine with AND
+// instead of the default OR.
+fn parse_default_op_flag(args: Vec<String>) -> (Op, Vec<String>) {
+    let mut default_op = Op::Or;
+    let mut remaining = Vec::new();
+
+    for arg in args.into_iter() {
+        if arg == "--and" {
+            default_op = Op::And;
+        } else {
+            remaining.push(arg);
+        }
+    }
+
+    (default_op, remaining)
+}
+
+// Pulls out `--fuzzy [k]`, which switches the engine from exact prefix matching to a
+// bounded edit-distance walk. `k` defaults to 2 when the flag is given without a value.
+fn parse_fuzzy_flag(args: Vec<String>) -> (Option<usize>, Vec<String>) {
+    let mut fuzzy_distance = None;
+    let mut remaining = Vec::new();
+
+    let mut iter = args.into_iter().peekable();
+    while let Some(arg) = iter.next() {
+        if arg == "--fuzzy" {
+            let distance = iter.peek()
+                .and_then(|next| next.parse::<usize>().ok())
+                .inspect(|_| { iter.next(); })
+                .unwrap_or(2);
+            fuzzy_distance = Some(distance);
+        } else {
+            remaining.push(arg);
+        }
+    }
 
-    matched_prefixes.sort_by(|(word1, _, score1), (word2, _, score2)| { 
-        if word1.as_str().eq(&sanitized_input) && word2.as_str().ne(&sanitized_input)  {
-            return Ordering::Greater;
-        } else if word1.as_str().ne(&sanitized_input) && word2.as_str().eq(&sanitized_input) {
-            return Ordering::Less;
+    (fuzzy_distance, remaining)
+}
+
+// Pulls out `--stop-words <path>`, `--min-length <n>` and `--max-length <n>` so the
+// corpus can be tuned without touching code.
+fn parse_tokenizer_flags(args: Vec<String>) -> (TokenizerConfig, Vec<String>) {
+    let (stop_words_path, args) = parse_value_flag(args, "--stop-words", String::new());
+    let (min_length, args) = parse_value_flag(args, "--min-length", DEFAULT_MIN_TOKEN_LENGTH.to_string());
+    let (max_length, remaining) = parse_value_flag(args, "--max-length", DEFAULT_MAX_TOKEN_LENGTH.to_string());
+
+    let min_length = min_length.parse::<usize>().expect("--min-length must be a number");
+    let max_length = max_length.parse::<usize>().expect("--max-length must be a number");
+
+    let stop_words_path = if stop_words_path.is_empty() { None } else { Some(PathBuf::from(stop_words_path)) };
+    let stop_words = load_stop_words(stop_words_path.as_deref()).expect("Could not load stop-word list");
+
+    (TokenizerConfig::new(min_length, max_length, stop_words), remaining)
+}
+
+// Pulls out `--flag <value>`, falling back to `default` when the flag isn't present.
+fn parse_value_flag(args: Vec<String>, flag: &str, default: String) -> (String, Vec<String>) {
+    let mut value = default;
+    let mut remaining = Vec::new();
+
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            value = iter.next().unwrap_or_else(|| panic!("{} requires a value", flag));
+        } else {
+            remaining.push(arg);
         }
-        score1.cmp(score2)
+    }
+
+    (value, remaining)
+}
+
+fn fuzzy_search(input: &str, max_distance: usize, tokenizer_config: &TokenizerConfig, search_trie: &mut Trie<BString, BTreeMap<BigDecimal, String>>) {
+    let sanitized_input = tokenizer_config.tokenize(input).into_iter().next().unwrap_or_default();
+
+    let mut ranked_documents: Vec<(String, usize, f64)> = fuzzy_resolve(search_trie, &sanitized_input, max_distance)
+        .into_iter()
+        .map(|(doc, (distance, score))| (doc, distance, score))
+        .collect();
+
+    ranked_documents.sort_by(|(_, distance1, score1), (_, distance2, score2)| {
+        distance1.cmp(distance2).then_with(|| score2.partial_cmp(score1).unwrap_or(Ordering::Equal))
     });
-    matched_prefixes.reverse();
 
-    if matched_prefixes.is_empty() {
+    if ranked_documents.is_empty() {
+        println!("No matches");
+    } else {
+        ranked_documents.iter().take(100)
+            .for_each(|(doc, distance, score)| {
+                println!("Email {} matched within edit distance {} with score {}", doc, distance, score);
+            });
+    }
+}
+
+fn search(input: &str, default_op: Op, tokenizer_config: &TokenizerConfig, search_index: &mut SearchIndex) {
+    let query = parse_query(input, default_op, tokenizer_config);
+
+    let mut ranked_documents: Vec<(String, f64)> = eval_query(&query, search_index).into_iter().collect();
+
+    ranked_documents.sort_by(|(_, score1), (_, score2)| score1.partial_cmp(score2).unwrap_or(Ordering::Equal));
+    ranked_documents.reverse();
+
+    if ranked_documents.is_empty() {
         println!("No matches");
     } else {
-        matched_prefixes.iter().take(100)
-            .for_each(|(word, doc, score)|{
-                println!("Email {} matching word {} with score {}", doc, word.as_str(), score);
+        ranked_documents.iter().take(100)
+            .for_each(|(doc, score)| {
+                println!("Email {} matched with score {}", doc, score);
             });
     }
 }
@@ -109,108 +322,64 @@ fn build_search_trie(tf_idf: HashMap<String, HashMap<String, f64>>) -> Trie<BStr
     search_trie
 }
 
-fn calc_tf_idf(document_word_freq: HashMap<String, HashMap<String, u64>>, inverse_document_frequency: HashMap<String, f64>) -> HashMap<String, HashMap<String, f64>> {
-    let document_tf_score = document_word_freq.into_iter()
-        .map(|(document, word_freq)| {
-            let total_word_count = word_freq.values().sum::<u64>(); 
-            let term_frequency = word_freq
-                .into_iter()
-                .map(|(word, doc_word_count)| 
-                    (word.to_owned(), 
-                    doc_word_count as f64 / total_word_count as f64))
-                .collect::<HashMap<String, f64>>();
-            (document, term_frequency)
-        })
-        .collect::<HashMap<String, HashMap<String, f64>>>();
-
-    let doc_tf_idf = document_tf_score.iter()
-        .map(|(document, tf_scores)| {
-            let tf_idf_scores = tf_scores.into_iter()
-                .map(|(word, tf_score)| {
-                    (word.to_owned(), tf_score * inverse_document_frequency.get(word).unwrap_or(&0.0_f64))
-                })
-            .collect::<HashMap<String, f64>>();
-            
-            let normalized_tf_idf = l2_normalize(tf_idf_scores);
-
-            (document.to_string(), normalized_tf_idf)
-        }) 
-        .collect::<HashMap<String, HashMap<String, f64>>>();
-
-    doc_tf_idf
-}
-
-fn l2_normalize(tf_id: HashMap<String, f64>) -> HashMap<String, f64> {
-    let l2_norm = tf_id
-        .values()
-        .map(|value| value * value)
-        .sum::<f64>()
-        .sqrt();
-
-    tf_id
-        .iter()
-        .map(|(key, value)| (key.clone(), value / l2_norm))
-        .collect::<HashMap<String, f64>>()
-}
-
-fn calc_inverse_document_freq(document_word_freq: HashMap<String, HashMap<String, u64>>) -> HashMap<String, f64> {
-    let total_word_freq = document_word_freq
-        .values()
-        .fold( HashMap::new(), 
-            |mut acc, doc_map| {
-                doc_map.into_iter()
-                    .for_each(|(k, v)| {
-                        acc.entry(k).and_modify(|v| *v += 1).or_insert(1);
-                    });
-                acc });
-    
-    let total_document_count = document_word_freq.keys().len();
-
-    let inverse_document_frequency = total_word_freq
-        .into_iter()
-        .map(|(word, count)| {
-            let documents_with_term = (total_document_count as f64 + 1.0) / (count as f64 + 1.0);
-            (word.to_owned(), documents_with_term.ln() + 1.0)
-        })
-        .collect::<HashMap<String, f64>>();
-    inverse_document_frequency
-}
-
-fn get_document_word_freq(email_file_paths: Vec<PathBuf>) -> HashMap<String, HashMap<String, u64>> {
+fn get_document_word_freq(email_file_paths: &[PathBuf], tokenizer_config: &TokenizerConfig) -> HashMap<String, HashMap<String, u64>> {
     email_file_paths.iter()
             .map(|file_path| {
-                let file_name = file_path.clone().as_os_str().to_str()
-                    .expect("OS string path contained invalid valid UTF8").to_owned();
-                
-                let mut file = File::open(file_path).expect("File could not be opened from path");
-                let mut buf = vec![];
-                file.read_to_end(&mut buf).expect("File could not be read into byte buffer");
-                
-                // Removes not UTF8 characters from emails
-                let file_content = String::from_utf8_lossy (&buf).into_owned();
-
-                let words_in_file = file_content
-                    .split_whitespace()
-                    .collect::<Vec<&str>>();
+                let file_name = file_name_of(file_path);
+                let parsed_email = parse_email(&read_file_content(file_path));
+
                 let mut word_count: HashMap<String, u64> = HashMap::new();
-                
-                for word in words_in_file.iter() {
-                    let sanitized_word = word
-                        .to_lowercase()
-                        .chars()
-                        .filter(|ch| !PUNCTUATION_CHARS.contains(&ch.to_string()))
-                        .collect::<String>();
-                    word_count.entry(sanitized_word).and_modify(|count| *count += 1).or_insert(1);
+
+                for token in tokenizer_config.tokenize(&parsed_email.body) {
+                    word_count.entry(token).and_modify(|count| *count += 1).or_insert(1);
                 }
-            
+
                 (file_name, word_count)
             })
             .collect::<HashMap<String, HashMap<String, u64>>>()
 }
 
+// Builds the per-field term sets (from/to/cc/subject/date) that back `field:value`
+// queries, keeping header noise like `subject` and `cc` out of the body word counts.
+fn get_document_field_terms(email_file_paths: &[PathBuf], tokenizer_config: &TokenizerConfig) -> HashMap<String, HashMap<String, HashSet<String>>> {
+    email_file_paths.iter()
+            .map(|file_path| {
+                let file_name = file_name_of(file_path);
+                let parsed_email = parse_email(&read_file_content(file_path));
+
+                let field_terms = INDEXED_FIELDS.iter()
+                    .filter_map(|field| {
+                        parsed_email.headers.get(*field).map(|value| {
+                            (field.to_string(), tokenizer_config.tokenize(value).into_iter().collect::<HashSet<String>>())
+                        })
+                    })
+                    .collect::<HashMap<String, HashSet<String>>>();
+
+                (file_name, field_terms)
+            })
+            .collect::<HashMap<String, HashMap<String, HashSet<String>>>>()
+}
+
+fn file_name_of(file_path: &Path) -> String {
+    file_path.as_os_str().to_str()
+        .expect("OS string path contained invalid valid UTF8").to_owned()
+}
+
+fn read_file_content(file_path: &Path) -> String {
+    let mut file = File::open(file_path).expect("File could not be opened from path");
+    let mut buf = vec![];
+    file.read_to_end(&mut buf).expect("File could not be read into byte buffer");
+
+    // Removes not UTF8 characters from emails
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
 
-fn get_email_paths_from_dir(path: impl AsRef<Path>) -> std::io::Result<Vec<PathBuf>> {
+// Walks the corpus directory once, collecting both the file paths and the most recent
+// modification time seen, so staleness checks don't need a second filesystem pass.
+fn get_email_paths_from_dir(path: impl AsRef<Path>) -> std::io::Result<(Vec<PathBuf>, SystemTime)> {
     let mut buf = vec![];
+    let mut latest_mtime = SystemTime::UNIX_EPOCH;
     let entries = fs::read_dir(path)?;
 
     for entry in entries {
@@ -218,14 +387,16 @@ fn get_email_paths_from_dir(path: impl AsRef<Path>) -> std::io::Result<Vec<PathB
         let meta = entry.metadata()?;
 
         if meta.is_dir() {
-            let mut subdir = get_email_paths_from_dir(entry.path())?;
+            let (mut subdir, sub_latest_mtime) = get_email_paths_from_dir(entry.path())?;
             buf.append(&mut subdir);
+            latest_mtime = latest_mtime.max(sub_latest_mtime);
         }
 
         if meta.is_file() {
+            latest_mtime = latest_mtime.max(meta.modified()?);
             buf.push(entry.path());
         }
     }
 
-    Ok(buf)
+    Ok((buf, latest_mtime))
 }
\ No newline at end of file
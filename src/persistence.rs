@@ -0,0 +1,45 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::{CorpusStats, ScoringMode};
+use crate::tokenizer::{TokenizerConfig, TokenizerFingerprint};
+
+const INDEX_FILE_NAME: &str = "index.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct PersistedIndex {
+    pub scored_documents: HashMap<String, HashMap<String, f64>>,
+    pub corpus_stats: CorpusStats,
+    pub field_index: HashMap<String, HashMap<String, HashSet<String>>>,
+    pub source_mtime: u64,
+    pub scoring_mode: ScoringMode,
+    pub tokenizer_fingerprint: TokenizerFingerprint,
+}
+
+fn index_path(data_dir: impl AsRef<Path>) -> PathBuf {
+    data_dir.as_ref().join(INDEX_FILE_NAME)
+}
+
+// Loads the persisted index only if it was built from sources no older than
+// `source_mtime` using the same scoring mode and tokenizer settings requested now;
+// a stale, missing or mismatched index falls through to a fresh build.
+pub fn load_if_fresh(data_dir: impl AsRef<Path>, source_mtime: u64, scoring_mode: ScoringMode, tokenizer_config: &TokenizerConfig) -> Option<PersistedIndex> {
+    let contents = fs::read_to_string(index_path(&data_dir)).ok()?;
+    let persisted: PersistedIndex = serde_json::from_str(&contents).ok()?;
+
+    let fresh = persisted.source_mtime >= source_mtime
+        && persisted.scoring_mode == scoring_mode
+        && persisted.tokenizer_fingerprint == tokenizer_config.fingerprint();
+
+    if fresh { Some(persisted) } else { None }
+}
+
+pub fn save(data_dir: impl AsRef<Path>, persisted: &PersistedIndex) -> io::Result<()> {
+    fs::create_dir_all(&data_dir)?;
+    let contents = serde_json::to_string(persisted).expect("Index is serializable");
+    fs::write(index_path(&data_dir), contents)
+}
@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use bigdecimal::{BigDecimal, ToPrimitive};
+use qp_trie::{wrapper::BString, Trie};
+
+use crate::email::INDEXED_FIELDS;
+use crate::tokenizer::TokenizerConfig;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    And,
+    Or,
+    Not,
+}
+
+#[derive(Debug, Clone)]
+pub enum QueryNode {
+    Term(String),
+    FieldTerm(String, String),
+    // A term that tokenized to nothing (a stop word, or outside the min/max length
+    // bounds) or a query with no terms at all. Resolves to zero matches rather than
+    // falling back to an empty-string prefix lookup, which would match every document.
+    Empty,
+    BinOp(Op, Box<QueryNode>, Box<QueryNode>),
+}
+
+// Folds sub-terms produced by splitting a single query token into an AND chain, so
+// e.g. `jeff.skilling` (which the shared tokenizer splits into `jeff` and `skilling`,
+// same as indexing does) requires both to be present rather than matching neither.
+// A token that tokenizes to nothing resolves to `Empty` instead of an empty string,
+// since an empty-string prefix lookup would match every key in the trie.
+fn chain_and(nodes: Vec<QueryNode>) -> QueryNode {
+    let mut nodes = nodes.into_iter();
+    let first = nodes.next().unwrap_or(QueryNode::Empty);
+    nodes.fold(first, |acc, node| QueryNode::BinOp(Op::And, Box::new(acc), Box::new(node)))
+}
+
+// A bare term searches the body trie; `field:value` (e.g. `from:skilling`) instead
+// looks the value up in that field's sub-index. Both run the raw text through the
+// same `TokenizerConfig` used at index time, so `jeff.skilling@enron.com` resolves
+// to the same tokens the indexer put in the trie/field index.
+fn parse_term(token: &str, tokenizer_config: &TokenizerConfig) -> QueryNode {
+    if let Some((field, value)) = token.split_once(':') {
+        let field = field.to_lowercase();
+        if INDEXED_FIELDS.contains(&field.as_str()) {
+            let terms = tokenizer_config.tokenize(value)
+                .into_iter()
+                .map(|term| QueryNode::FieldTerm(field.clone(), term))
+                .collect();
+            return chain_and(terms);
+        }
+    }
+
+    let terms = tokenizer_config.tokenize(token).into_iter().map(QueryNode::Term).collect();
+    chain_and(terms)
+}
+
+// Parses queries like `enron AND fraud NOT legal` into a left-folded operator tree.
+// A term with no explicit operator before it falls back to `default_op`. An
+// empty/whitespace-only query resolves to `Empty` rather than panicking.
+pub fn parse_query(input: &str, default_op: Op, tokenizer_config: &TokenizerConfig) -> QueryNode {
+    let mut tokens = input.split_whitespace();
+    let first = match tokens.next() {
+        Some(first) => first,
+        None => return QueryNode::Empty,
+    };
+    let mut node = parse_term(first, tokenizer_config);
+    let mut pending_op: Option<Op> = None;
+
+    for token in tokens {
+        match token.to_uppercase().as_str() {
+            "AND" => pending_op = Some(Op::And),
+            "OR" => pending_op = Some(Op::Or),
+            "NOT" => pending_op = Some(Op::Not),
+            _ => {
+                let op = pending_op.take().unwrap_or(default_op);
+                node = QueryNode::BinOp(op, Box::new(node), Box::new(parse_term(token, tokenizer_config)));
+            }
+        }
+    }
+
+    node
+}
+
+// Bundles the body trie with the per-field sub-index so a query can resolve either
+// kind of term without threading two arguments through every call.
+pub struct SearchIndex<'a> {
+    pub body_trie: &'a mut Trie<BString, BTreeMap<BigDecimal, String>>,
+    pub field_index: &'a HashMap<String, HashMap<String, HashSet<String>>>,
+}
+
+fn resolve_term(trie: &mut Trie<BString, BTreeMap<BigDecimal, String>>, term: &str) -> HashMap<String, f64> {
+    trie.iter_prefix_mut(&BString::from(term.to_owned()))
+        .fold(HashMap::new(), |mut acc, (_word, doc_scores)| {
+            doc_scores.iter().for_each(|(score, doc)| {
+                let score = score.to_f64().unwrap_or(0.0);
+                acc.entry(doc.clone()).and_modify(|existing| *existing += score).or_insert(score);
+            });
+            acc
+        })
+}
+
+fn resolve_field_term(field_index: &HashMap<String, HashMap<String, HashSet<String>>>, field: &str, term: &str) -> HashMap<String, f64> {
+    field_index.get(field)
+        .and_then(|word_docs| word_docs.get(term))
+        .map(|docs| docs.iter().map(|doc| (doc.clone(), 1.0)).collect())
+        .unwrap_or_default()
+}
+
+// Evaluates the operator tree into a DocId -> score accumulator: AND intersects,
+// OR unions and sums, NOT subtracts the right-hand matches from the left.
+pub fn eval_query(node: &QueryNode, index: &mut SearchIndex) -> HashMap<String, f64> {
+    match node {
+        QueryNode::Term(term) => resolve_term(index.body_trie, term),
+        QueryNode::FieldTerm(field, term) => resolve_field_term(index.field_index, field, term),
+        QueryNode::Empty => HashMap::new(),
+        QueryNode::BinOp(Op::And, left, right) => {
+            let left_matches = eval_query(left, index);
+            let right_matches = eval_query(right, index);
+            left_matches
+                .into_iter()
+                .filter_map(|(doc, score)| right_matches.get(&doc).map(|right_score| (doc, score + right_score)))
+                .collect()
+        }
+        QueryNode::BinOp(Op::Or, left, right) => {
+            let mut left_matches = eval_query(left, index);
+            let right_matches = eval_query(right, index);
+            right_matches.into_iter().for_each(|(doc, score)| {
+                left_matches.entry(doc).and_modify(|existing| *existing += score).or_insert(score);
+            });
+            left_matches
+        }
+        QueryNode::BinOp(Op::Not, left, right) => {
+            let left_matches = eval_query(left, index);
+            let right_matches = eval_query(right, index);
+            left_matches.into_iter().filter(|(doc, _)| !right_matches.contains_key(doc)).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::FromPrimitive;
+
+    use super::*;
+    use crate::tokenizer::TokenizerConfig;
+
+    fn tokenizer_config() -> TokenizerConfig {
+        TokenizerConfig::new(1, 32, HashSet::new())
+    }
+
+    fn body_trie() -> Trie<BString, BTreeMap<BigDecimal, String>> {
+        let mut trie = Trie::new();
+        trie.insert(BString::from("enron"), BTreeMap::from([
+            (BigDecimal::from_f64(1.0).unwrap(), "doc1".to_string()),
+            (BigDecimal::from_f64(2.0).unwrap(), "doc2".to_string()),
+        ]));
+        trie.insert(BString::from("fraud"), BTreeMap::from([
+            (BigDecimal::from_f64(1.0).unwrap(), "doc1".to_string()),
+        ]));
+        trie.insert(BString::from("legal"), BTreeMap::from([
+            (BigDecimal::from_f64(1.0).unwrap(), "doc2".to_string()),
+        ]));
+        trie
+    }
+
+    fn field_index() -> HashMap<String, HashMap<String, HashSet<String>>> {
+        HashMap::from([
+            ("from".to_string(), HashMap::from([
+                ("skilling".to_string(), HashSet::from(["doc1".to_string()])),
+            ])),
+        ])
+    }
+
+    fn run(input: &str, default_op: Op) -> HashMap<String, f64> {
+        let mut trie = body_trie();
+        let field_index = field_index();
+        let query = parse_query(input, default_op, &tokenizer_config());
+        let mut index = SearchIndex { body_trie: &mut trie, field_index: &field_index };
+        eval_query(&query, &mut index)
+    }
+
+    #[test]
+    fn and_keeps_only_documents_matching_both_terms() {
+        let matches = run("enron AND fraud", Op::Or);
+        assert_eq!(matches.keys().collect::<HashSet<_>>(), HashSet::from([&"doc1".to_string()]));
+    }
+
+    #[test]
+    fn or_unions_and_sums_scores() {
+        let matches = run("fraud OR legal", Op::And);
+        assert_eq!(matches.keys().collect::<HashSet<_>>(), HashSet::from([&"doc1".to_string(), &"doc2".to_string()]));
+        // "enron" matches both documents, so ORing it with "fraud" should sum doc1's score.
+        let with_enron = run("enron OR fraud", Op::Or);
+        assert_eq!(with_enron["doc1"], 1.0 + 1.0);
+    }
+
+    #[test]
+    fn not_removes_documents_matched_by_the_right_hand_side() {
+        let matches = run("enron NOT legal", Op::Or);
+        assert_eq!(matches.keys().collect::<HashSet<_>>(), HashSet::from([&"doc1".to_string()]));
+    }
+
+    #[test]
+    fn bare_terms_default_to_the_given_operator() {
+        // With default_op = And, "enron fraud" behaves like "enron AND fraud".
+        let matches = run("enron fraud", Op::And);
+        assert_eq!(matches.keys().collect::<HashSet<_>>(), HashSet::from([&"doc1".to_string()]));
+    }
+
+    #[test]
+    fn field_term_resolves_against_the_field_index() {
+        let matches = run("from:skilling", Op::Or);
+        assert_eq!(matches.keys().collect::<HashSet<_>>(), HashSet::from([&"doc1".to_string()]));
+    }
+
+    #[test]
+    fn a_stop_word_matches_nothing_instead_of_every_document() {
+        let stop_words = HashSet::from(["the".to_string()]);
+        let mut trie = body_trie();
+        let field_index = field_index();
+        let query = parse_query("the", Op::Or, &TokenizerConfig::new(1, 32, stop_words));
+        let mut index = SearchIndex { body_trie: &mut trie, field_index: &field_index };
+
+        assert!(eval_query(&query, &mut index).is_empty());
+    }
+
+    #[test]
+    fn a_field_value_that_tokenizes_to_nothing_matches_nothing() {
+        let stop_words = HashSet::from(["re".to_string()]);
+        let mut trie = body_trie();
+        let field_index = field_index();
+        let query = parse_query("subject:re", Op::Or, &TokenizerConfig::new(1, 32, stop_words));
+        let mut index = SearchIndex { body_trie: &mut trie, field_index: &field_index };
+
+        assert!(eval_query(&query, &mut index).is_empty());
+    }
+
+    #[test]
+    fn an_empty_query_matches_nothing_instead_of_panicking() {
+        let matches = run("   ", Op::Or);
+        assert!(matches.is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScoringMode {
+    TfIdf,
+    Bm25,
+}
+
+impl ScoringMode {
+    pub fn from_flag(flag: &str) -> Option<ScoringMode> {
+        match flag {
+            "tfidf" | "tf-idf" => Some(ScoringMode::TfIdf),
+            "bm25" => Some(ScoringMode::Bm25),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CorpusStats {
+    pub doc_lengths: HashMap<String, u64>,
+    pub avgdl: f64,
+    pub doc_freq: HashMap<String, u64>,
+    pub total_term_freq: HashMap<String, u64>,
+    pub total_documents: usize,
+}
+
+pub fn calc_corpus_stats(document_word_freq: &HashMap<String, HashMap<String, u64>>) -> CorpusStats {
+    let doc_lengths = document_word_freq
+        .iter()
+        .map(|(doc, word_freq)| (doc.clone(), word_freq.values().sum::<u64>()))
+        .collect::<HashMap<String, u64>>();
+
+    let total_documents = document_word_freq.keys().len();
+
+    let avgdl = if total_documents == 0 {
+        0.0
+    } else {
+        doc_lengths.values().sum::<u64>() as f64 / total_documents as f64
+    };
+
+    let (doc_freq, total_term_freq) = document_word_freq
+        .values()
+        .fold((HashMap::new(), HashMap::new()), |(mut doc_freq, mut total_term_freq), doc_map| {
+            doc_map.iter().for_each(|(word, count)| {
+                doc_freq.entry(word.to_owned()).and_modify(|v| *v += 1).or_insert(1);
+                total_term_freq.entry(word.to_owned()).and_modify(|v| *v += count).or_insert(*count);
+            });
+            (doc_freq, total_term_freq)
+        });
+
+    CorpusStats { doc_lengths, avgdl, doc_freq, total_term_freq, total_documents }
+}
+
+fn calc_bm25_idf(n_t: u64, total_documents: usize) -> f64 {
+    ((total_documents as f64 - n_t as f64 + 0.5) / (n_t as f64 + 0.5) + 1.0).ln()
+}
+
+// BM25 scoring per Robertson/Sparck Jones, with defaults k1 = 1.2, b = 0.75.
+pub fn calc_bm25(document_word_freq: HashMap<String, HashMap<String, u64>>, corpus_stats: &CorpusStats) -> HashMap<String, HashMap<String, f64>> {
+    document_word_freq
+        .into_iter()
+        .map(|(document, word_freq)| {
+            let doc_len = *corpus_stats.doc_lengths.get(&document).unwrap_or(&0) as f64;
+            let length_norm = 1.0 - BM25_B + BM25_B * doc_len / corpus_stats.avgdl;
+
+            let bm25_scores = word_freq
+                .into_iter()
+                .map(|(word, freq)| {
+                    let f_td = freq as f64;
+                    let n_t = *corpus_stats.doc_freq.get(&word).unwrap_or(&0);
+                    let idf = calc_bm25_idf(n_t, corpus_stats.total_documents);
+                    let score = idf * (f_td * (BM25_K1 + 1.0)) / (f_td + BM25_K1 * length_norm);
+                    (word, score)
+                })
+                .collect::<HashMap<String, f64>>();
+
+            (document, bm25_scores)
+        })
+        .collect::<HashMap<String, HashMap<String, f64>>>()
+}
+
+pub fn calc_tf_idf(document_word_freq: HashMap<String, HashMap<String, u64>>, inverse_document_frequency: HashMap<String, f64>) -> HashMap<String, HashMap<String, f64>> {
+    let document_tf_score = document_word_freq.into_iter()
+        .map(|(document, word_freq)| {
+            let total_word_count = word_freq.values().sum::<u64>();
+            let term_frequency = word_freq
+                .into_iter()
+                .map(|(word, doc_word_count)|
+                    (word.to_owned(),
+                    doc_word_count as f64 / total_word_count as f64))
+                .collect::<HashMap<String, f64>>();
+            (document, term_frequency)
+        })
+        .collect::<HashMap<String, HashMap<String, f64>>>();
+
+    let doc_tf_idf = document_tf_score.iter()
+        .map(|(document, tf_scores)| {
+            let tf_idf_scores = tf_scores.into_iter()
+                .map(|(word, tf_score)| {
+                    (word.to_owned(), tf_score * inverse_document_frequency.get(word).unwrap_or(&0.0_f64))
+                })
+            .collect::<HashMap<String, f64>>();
+
+            let normalized_tf_idf = l2_normalize(tf_idf_scores);
+
+            (document.to_string(), normalized_tf_idf)
+        })
+        .collect::<HashMap<String, HashMap<String, f64>>>();
+
+    doc_tf_idf
+}
+
+fn l2_normalize(tf_id: HashMap<String, f64>) -> HashMap<String, f64> {
+    let l2_norm = tf_id
+        .values()
+        .map(|value| value * value)
+        .sum::<f64>()
+        .sqrt();
+
+    tf_id
+        .iter()
+        .map(|(key, value)| (key.clone(), value / l2_norm))
+        .collect::<HashMap<String, f64>>()
+}
+
+pub fn calc_inverse_document_freq(document_word_freq: HashMap<String, HashMap<String, u64>>) -> HashMap<String, f64> {
+    let total_word_freq = document_word_freq
+        .values()
+        .fold( HashMap::new(),
+            |mut acc, doc_map| {
+                doc_map.into_iter()
+                    .for_each(|(k, v)| {
+                        acc.entry(k).and_modify(|v| *v += 1).or_insert(1);
+                    });
+                acc });
+
+    let total_document_count = document_word_freq.keys().len();
+
+    let inverse_document_frequency = total_word_freq
+        .into_iter()
+        .map(|(word, count)| {
+            let documents_with_term = (total_document_count as f64 + 1.0) / (count as f64 + 1.0);
+            (word.to_owned(), documents_with_term.ln() + 1.0)
+        })
+        .collect::<HashMap<String, f64>>();
+    inverse_document_frequency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus() -> HashMap<String, HashMap<String, u64>> {
+        HashMap::from([
+            ("a".to_string(), HashMap::from([("foo".to_string(), 2)])),
+            ("b".to_string(), HashMap::from([("foo".to_string(), 1), ("bar".to_string(), 1)])),
+        ])
+    }
+
+    fn assert_approx(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn bm25_scores_match_the_formula() {
+        let document_word_freq = corpus();
+        let corpus_stats = calc_corpus_stats(&document_word_freq);
+        let scores = calc_bm25(document_word_freq, &corpus_stats);
+
+        // idf(foo) = ln((2 - 2 + 0.5) / (2 + 0.5) + 1), idf(bar) = ln((2 - 1 + 0.5) / (1 + 0.5) + 1)
+        let idf_foo = ((2.0_f64 - 2.0 + 0.5) / (2.0 + 0.5) + 1.0).ln();
+        let idf_bar = ((2.0_f64 - 1.0 + 0.5) / (1.0 + 0.5) + 1.0).ln();
+
+        // Both documents have length 2 and avgdl is 2, so the length-norm term is 1.0.
+        assert_approx(scores["a"]["foo"], idf_foo * (2.0 * 2.2) / (2.0 + 1.2));
+        assert_approx(scores["b"]["foo"], idf_foo * (1.0 * 2.2) / (1.0 + 1.2));
+        assert_approx(scores["b"]["bar"], idf_bar * (1.0 * 2.2) / (1.0 + 1.2));
+    }
+
+    #[test]
+    fn tf_idf_scores_are_l2_normalized() {
+        let document_word_freq = corpus();
+        let inverse_document_frequency = calc_inverse_document_freq(document_word_freq.clone());
+        let scores = calc_tf_idf(document_word_freq, inverse_document_frequency);
+
+        // "a" has a single term, so its l2-normalized score is always 1.0.
+        assert_approx(scores["a"]["foo"], 1.0);
+
+        let norm = (scores["b"]["foo"].powi(2) + scores["b"]["bar"].powi(2)).sqrt();
+        assert_approx(norm, 1.0);
+        // "bar" is rarer than "foo" (lower document frequency), so it should score higher.
+        assert!(scores["b"]["bar"] > scores["b"]["foo"]);
+    }
+
+    #[test]
+    fn inverse_document_frequency_is_higher_for_rarer_terms() {
+        let idf = calc_inverse_document_freq(corpus());
+        assert!(idf["bar"] > idf["foo"]);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::scoring::CorpusStats;
+
+pub struct TermStat {
+    pub word: String,
+    pub document_frequency: u64,
+    pub total_occurrences: u64,
+}
+
+pub fn top_terms(corpus_stats: &CorpusStats, limit: usize) -> Vec<TermStat> {
+    let mut terms = corpus_stats.total_term_freq
+        .iter()
+        .map(|(word, total_occurrences)| TermStat {
+            word: word.clone(),
+            document_frequency: *corpus_stats.doc_freq.get(word).unwrap_or(&0),
+            total_occurrences: *total_occurrences,
+        })
+        .collect::<Vec<TermStat>>();
+
+    terms.sort_by_key(|term| std::cmp::Reverse(term.total_occurrences));
+    terms.truncate(limit);
+    terms
+}
+
+pub fn print_csv(terms: &[TermStat]) {
+    println!("word,document_frequency,total_occurrences");
+    terms.iter().for_each(|term| {
+        println!("{},{},{}", csv_escape(&term.word), term.document_frequency, term.total_occurrences);
+    });
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::PUNCTUATION_CHARS;
+
+pub struct TokenizerConfig {
+    separators: HashSet<char>,
+    min_length: usize,
+    max_length: usize,
+    stop_words: HashSet<String>,
+}
+
+// The subset of `TokenizerConfig` that changes what gets tokenized, so a persisted
+// index can be compared against the tokenizer a `search`/`build-index` invocation
+// asked for and treated as stale on any mismatch.
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenizerFingerprint {
+    min_length: usize,
+    max_length: usize,
+    stop_words: BTreeSet<String>,
+}
+
+impl TokenizerConfig {
+    pub fn new(min_length: usize, max_length: usize, stop_words: HashSet<String>) -> TokenizerConfig {
+        let separators = PUNCTUATION_CHARS.iter()
+            .filter_map(|punctuation| punctuation.chars().next())
+            .collect::<HashSet<char>>();
+
+        TokenizerConfig { separators, min_length, max_length, stop_words }
+    }
+
+    pub fn fingerprint(&self) -> TokenizerFingerprint {
+        TokenizerFingerprint {
+            min_length: self.min_length,
+            max_length: self.max_length,
+            stop_words: self.stop_words.iter().cloned().collect(),
+        }
+    }
+
+    // Splits on whitespace and the configured separator set (so `john@enron.com`
+    // becomes `john`, `enron`, `com` rather than one mangled token), then drops
+    // anything outside the length bounds or on the stop-word list.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        text.to_lowercase()
+            .split(|ch: char| ch.is_whitespace() || self.separators.contains(&ch))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.to_string())
+            .filter(|token| {
+                let token_length = token.chars().count();
+                token_length >= self.min_length && token_length <= self.max_length
+            })
+            .filter(|token| !self.stop_words.contains(token))
+            .collect()
+    }
+}
+
+pub fn load_stop_words(path: Option<&Path>) -> io::Result<HashSet<String>> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(default_stop_words()),
+    };
+
+    let contents = fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(|line| line.trim().to_lowercase())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+fn default_stop_words() -> HashSet<String> {
+    [
+        "a", "an", "and", "are", "as", "at", "be", "been", "but", "by", "for", "from",
+        "in", "is", "it", "its", "of", "on", "or", "that", "the", "this", "to", "was", "were", "with",
+        "subject", "cc", "bcc", "forwarded", "fwd", "re",
+    ]
+        .iter()
+        .map(|word| word.to_string())
+        .collect()
+}